@@ -1,31 +1,450 @@
 //! Preload all fonts into the GPU textures
 
-use std::num::NonZeroU64;
-use wgpu::util::DeviceExt;
+use std::collections::HashMap;
 use image::GenericImageView;
-use crate::Castable;
 
-/// An instance of a font
-pub struct Font {
-    /// Bind group for this font
+/// Width and height, in pixels, of the shared atlas texture when it's
+/// first created; [`ShelfPacker`] doubles this on demand if the glyphs
+/// packed into it don't fit
+const ATLAS_SIZE: u32 = 1024;
+
+/// How much a shelf's height may exceed a glyph's height and still be
+/// considered a fit, so near-miss glyph sizes don't each open a new shelf
+const SHELF_TOLERANCE: u32 = 4;
+
+/// A single packed row within a [`ShelfPacker`]
+struct Shelf {
+    /// Y position of this shelf within the atlas
+    y: u32,
+
+    /// Height of the tallest item packed into this shelf so far
+    height: u32,
+
+    /// Next unused x pixel on this shelf
+    cursor_x: u32,
+}
+
+/// The pure skyline/shelf allocation math behind [`Atlas`], kept separate
+/// from any GPU state so it can be unit tested without a device
+///
+/// Doubles `size` whenever a glyph doesn't fit any existing shelf and
+/// there isn't room to open a new one, so callers never have to worry
+/// about the atlas running out of space.
+struct ShelfPacker {
+    /// Current width and height of the (square) atlas being packed into
+    size: u32,
+
+    /// Shelves packed so far, in order of increasing `y`
+    shelves: Vec<Shelf>,
+
+    /// Y position the next, as-yet-unopened shelf would start at
+    next_y: u32,
+}
+
+impl ShelfPacker {
+    fn new(size: u32) -> Self {
+        Self { size, shelves: Vec::new(), next_y: 0 }
+    }
+
+    /// Allocate room for a `width x height` item, growing `size` (doubling
+    /// it, as many times as needed) if nothing currently fits, and return
+    /// its packed `(x, y)` origin
+    fn alloc(&mut self, width: u32, height: u32) -> (u32, u32) {
+        loop {
+            let shelf_idx = self.shelves.iter().position(|shelf| {
+                shelf.height >= height &&
+                shelf.height <= height + SHELF_TOLERANCE &&
+                self.size - shelf.cursor_x >= width
+            });
+
+            if let Some(shelf_idx) = shelf_idx {
+                let shelf = &mut self.shelves[shelf_idx];
+                let (x, y) = (shelf.cursor_x, shelf.y);
+                shelf.cursor_x += width;
+                return (x, y);
+            }
+
+            if self.next_y + height <= self.size {
+                self.shelves.push(Shelf {
+                    y:        self.next_y,
+                    height,
+                    cursor_x: 0,
+                });
+                self.next_y += height;
+                continue;
+            }
+
+            self.size = self.size.checked_mul(2)
+                .expect("Glyph too large to fit in the atlas even after growing");
+        }
+    }
+}
+
+/// A single growable GPU texture that every glyph, from every font and
+/// size, is packed into using a skyline/shelf allocator
+///
+/// Packing everything into one atlas means a whole frame of text can be
+/// drawn from a single bind group, instead of rebinding a different
+/// texture for every font used. When the packer runs out of room, the
+/// backing texture is recreated at double the size and the old contents
+/// are copied over -- the bind group layout is unaffected, so the render
+/// pipeline built from it stays valid.
+pub struct Atlas {
+    /// Backing texture glyph bitmaps are uploaded into
+    texture: wgpu::Texture,
+
+    /// Sampler used by `bind_group`, kept around so it can be reused
+    /// when the texture (and thus the bind group) is recreated
+    sampler: wgpu::Sampler,
+
+    /// Bind group layout for the atlas (texture at 0, sampler at 1)
+    pub bind_group_layout: wgpu::BindGroupLayout,
+
+    /// Bind group exposing the atlas texture and its sampler
     pub bind_group: wgpu::BindGroup,
 
-    /// Width of a character
+    /// Packing state for the atlas
+    packer: ShelfPacker,
+}
+
+impl Atlas {
+    /// Create an empty atlas, ready to have glyphs packed into it
+    fn new(device: &wgpu::Device) -> Self {
+        let texture = Self::create_texture(device, ATLAS_SIZE);
+        let sampler = Self::create_sampler(device);
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding:    0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        count:      None,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled:   false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type:    wgpu::TextureSampleType::Float {
+                                filterable: true
+                            },
+                        },
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding:    1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        count:      None,
+                        ty: wgpu::BindingType::Sampler(
+                            wgpu::SamplerBindingType::Filtering),
+                    },
+                ],
+                label: None,
+            });
+
+        let bind_group = Self::create_bind_group(
+            device, &bind_group_layout, &texture, &sampler);
+
+        Self {
+            texture,
+            sampler,
+            bind_group_layout,
+            bind_group,
+            packer: ShelfPacker::new(ATLAS_SIZE),
+        }
+    }
+
+    fn create_texture(device: &wgpu::Device, size: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width:                 size,
+                height:                size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count:    1,
+            dimension:       wgpu::TextureDimension::D2,
+            format:          wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage:           wgpu::TextureUsages::TEXTURE_BINDING |
+                             wgpu::TextureUsages::COPY_DST |
+                             wgpu::TextureUsages::COPY_SRC,
+            label:           None,
+            view_formats:    &[],
+        })
+    }
+
+    fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter:     wgpu::FilterMode::Linear,
+            min_filter:     wgpu::FilterMode::Linear,
+            mipmap_filter:  wgpu::FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
+    fn create_bind_group(
+        device:             &wgpu::Device,
+        bind_group_layout:  &wgpu::BindGroupLayout,
+        texture:            &wgpu::Texture,
+        sampler:            &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        let texture_view = texture.create_view(
+            &wgpu::TextureViewDescriptor::default());
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout:  bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding:  0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding:  1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: None,
+        })
+    }
+
+    /// Recreate the backing texture at `new_size`, copying the old
+    /// contents into the top-left corner of the new one, and rebuild the
+    /// bind group to point at it
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, new_size: u32) {
+        let old_texture = std::mem::replace(
+            &mut self.texture, Self::create_texture(device, new_size));
+        let old_size = old_texture.size().width;
+
+        let mut commands = device.create_command_encoder(&Default::default());
+        commands.copy_texture_to_texture(
+            wgpu::ImageCopyTexture {
+                texture:   &old_texture,
+                mip_level: 0,
+                origin:    wgpu::Origin3d::ZERO,
+                aspect:    wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTexture {
+                texture:   &self.texture,
+                mip_level: 0,
+                origin:    wgpu::Origin3d::ZERO,
+                aspect:    wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width:                 old_size,
+                height:                old_size,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(commands.finish()));
+
+        self.bind_group = Self::create_bind_group(
+            device, &self.bind_group_layout, &self.texture, &self.sampler);
+    }
+
+    /// Pack a `width x height` RGBA8 bitmap into the atlas and upload it
+    /// via `queue`, returning its packed rect as pixel-space
+    /// `(origin, origin + (width, height))`
+    ///
+    /// Finds the lowest existing shelf with enough height and remaining
+    /// width for the bitmap; if none fits, opens a new shelf at the
+    /// current top of the atlas, growing the backing texture first if
+    /// there isn't room for one.
+    ///
+    /// Deliberately pixel-space rather than normalized: growing the atlas
+    /// only ever recreates the texture at a larger size and copies the
+    /// old contents into its top-left corner, so a pixel-space rect
+    /// packed before a later grow is still exactly correct after it --
+    /// normalizing at pack time would bake in the atlas size as of that
+    /// moment, going stale the next time it grows. Callers normalize by
+    /// [`Atlas::size`] at render time instead, once it can no longer
+    /// change.
+    pub fn pack(
+        &mut self,
+        device: &wgpu::Device,
+        queue:  &wgpu::Queue,
+        width:  u32,
+        height: u32,
+        rgba:   &[u8],
+    ) -> ((f32, f32), (f32, f32)) {
+        let old_size = self.packer.size;
+        let (x, y) = self.packer.alloc(width, height);
+        if self.packer.size != old_size {
+            self.grow(device, queue, self.packer.size);
+        }
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture:   &self.texture,
+                mip_level: 0,
+                origin:    wgpu::Origin3d { x, y, z: 0 },
+                aspect:    wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::ImageDataLayout {
+                offset:         0,
+                bytes_per_row:  Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        (
+            (x as f32, y as f32),
+            ((x + width) as f32, (y + height) as f32),
+        )
+    }
+
+    /// Current width and height, in pixels, of the (square) backing
+    /// texture -- callers divide pixel-space rects by this, once packing
+    /// is done for good, to get normalized texture coordinates
+    pub fn size(&self) -> u32 {
+        self.packer.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShelfPacker;
+
+    /// The ten baked font sheets' pixel dimensions (16x16 grids of
+    /// 4x6 through 24x36 cells), in `load_fonts`'s loading order -- this
+    /// is a regression check for the atlas having once been sized too
+    /// small to fit this exact set of sheets
+    const FONT_SHEET_SIZES: &[(u32, u32)] = &[
+        (64, 96), (96, 128), (96, 160), (128, 192), (128, 224),
+        (128, 240), (128, 256), (192, 320), (256, 384), (384, 576),
+    ];
+
+    #[test]
+    fn packs_all_baked_font_sheets_without_panicking() {
+        let mut packer = ShelfPacker::new(1024);
+        for &(width, height) in FONT_SHEET_SIZES {
+            packer.alloc(width, height);
+        }
+    }
+
+    #[test]
+    fn earlier_rects_stay_valid_pixel_coordinates_after_a_later_grow() {
+        let mut packer = ShelfPacker::new(1024);
+        let rects: Vec<_> = FONT_SHEET_SIZES.iter()
+            .map(|&(width, height)| packer.alloc(width, height))
+            .collect();
+
+        // This exact font set overflows 1024 partway through, forcing a
+        // grow -- `Atlas::pack` must return pixel-space rects rather than
+        // normalizing by the atlas size as of each call, or every rect
+        // packed before this point would go stale once the atlas grows
+        assert!(packer.size > 1024, "packer should have grown past 1024");
+
+        for (&(width, height), &(x, y)) in FONT_SHEET_SIZES.iter().zip(&rects) {
+            assert!(x + width <= packer.size && y + height <= packer.size,
+                "rect ({x}, {y}) + ({width}, {height}) exceeds the final \
+                 atlas size {}", packer.size);
+        }
+    }
+
+    #[test]
+    fn grows_when_a_shelf_does_not_fit() {
+        let mut packer = ShelfPacker::new(64);
+        let (x1, y1) = packer.alloc(64, 64);
+        let (x2, y2) = packer.alloc(64, 64);
+
+        assert_eq!((x1, y1), (0, 0));
+        assert_eq!((x2, y2), (64, 0));
+        assert!(packer.size > 64, "packer should have grown past 64");
+    }
+
+    #[test]
+    fn reuses_a_shelf_within_tolerance() {
+        let mut packer = ShelfPacker::new(1024);
+        let (x1, y1) = packer.alloc(32, 32);
+        let (x2, y2) = packer.alloc(32, 30);
+
+        assert_eq!(y1, y2, "glyphs within SHELF_TOLERANCE should share a shelf");
+        assert_eq!(x2, x1 + 32);
+    }
+
+    #[test]
+    fn pack_1bpp_sets_bits_above_threshold() {
+        // A 16x2 RGBA8 "bitmap": top row alternates below/above the
+        // threshold, bottom row is all above it
+        let width  = 16;
+        let height = 2;
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for x in 0..width {
+            let sample = if x % 2 == 0 { 0u8 } else { 255u8 };
+            rgba.extend_from_slice(&[sample, sample, sample, sample]);
+        }
+        for _ in 0..width {
+            rgba.extend_from_slice(&[255, 255, 255, 255]);
+        }
+
+        let (bits, row_bytes) = super::pack_1bpp(&rgba, width, height, 100);
+
+        assert_eq!(row_bytes, 2, "16 pixels wide should pack to 2 bytes/row");
+        assert_eq!(bits.len(), (row_bytes * height) as usize);
+
+        // Top row: bit set only for odd x (the above-threshold samples)
+        assert_eq!(bits[0], 0b1010_1010);
+        assert_eq!(bits[1], 0b1010_1010);
+
+        // Bottom row: every sample is above the threshold
+        assert_eq!(bits[2], 0xFF);
+        assert_eq!(bits[3], 0xFF);
+    }
+}
+
+/// Where a font's glyph sheet lives in the shared compact (1-bit-per-pixel)
+/// bitmap buffer, when loaded with `compact = true`
+#[derive(Clone, Copy)]
+pub struct CompactSheet {
+    /// Byte offset of this font's sheet within the shared bitmap buffer
+    pub bit_offset: u32,
+
+    /// Bytes per row of the packed sheet (`ceil(sheet_width / 8)`)
+    pub row_bytes: u32,
+}
+
+/// A pre-baked, monospaced bitmap font whose 16x16 glyph sheet has been
+/// packed into the shared [`Atlas`], or, in compact mode, into the shared
+/// 1-bit-per-pixel bitmap buffer
+pub struct Font {
+    /// Width of a character, in pixels
     pub width: u32,
 
-    /// Height of a character
+    /// Height of a character, in pixels
     pub height: u32,
+
+    /// Pixel-space origin of this font's glyph sheet within the atlas
+    /// texture, as it was when packed -- normalize by [`Atlas::size`] at
+    /// render time, not here, since the atlas may have grown since
+    pub atlas_origin: (f32, f32),
+
+    /// Pixel-space width and height of this font's glyph sheet within
+    /// the atlas texture
+    pub atlas_scale: (f32, f32),
+
+    /// Present instead of a valid `atlas_origin`/`atlas_scale` when this
+    /// font was loaded in compact (1bpp) mode
+    pub compact: Option<CompactSheet>,
 }
 
 /// Loaded fonts
 pub struct Fonts {
-    /// Bind group layout for a font
-    ///
-    /// Contains the font texture at 0, and the sampler at 1
-    pub bind_group_layout: wgpu::BindGroupLayout,
+    /// Shared atlas every non-compact font's glyphs are packed into
+    pub atlas: Atlas,
 
-    /// All loaded fonts (in order of bindings)
+    /// All loaded fonts (in order of `FontSize`)
     pub fonts: Vec<Font>,
+
+    /// Shared 1-bit-per-pixel glyph buffer, present only when `load_fonts`
+    /// was called with `compact = true`
+    pub bitmap_buffer: Option<wgpu::Buffer>,
 }
 
 #[derive(Clone, Copy)]
@@ -43,8 +462,46 @@ pub enum FontSize {
     Size24x36,
 }
 
-/// Load all fonts in our database into the `device`
-pub fn load_fonts(device: &wgpu::Device, queue: &wgpu::Queue) -> Fonts {
+/// Pack an RGBA8 glyph sheet down to 1 bit per pixel, setting a bit
+/// wherever the sample's red channel exceeds `threshold`
+///
+/// Returns the packed bytes (row-major, 8 pixels per byte, no padding
+/// within a byte) and the number of bytes per row.
+fn pack_1bpp(
+    rgba:      &[u8],
+    width:     u32,
+    height:    u32,
+    threshold: u8,
+) -> (Vec<u8>, u32) {
+    let row_bytes = (width + 7) / 8;
+    let mut bits = vec![0u8; (row_bytes * height) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let sample = rgba[((y * width + x) * 4) as usize];
+            if sample > threshold {
+                let byte_idx = (y * row_bytes + x / 8) as usize;
+                bits[byte_idx] |= 1 << (x % 8);
+            }
+        }
+    }
+
+    (bits, row_bytes)
+}
+
+/// Load all fonts in our database
+///
+/// By default each font's 16x16 glyph sheet is packed into the shared
+/// atlas as full `Rgba8UnormSrgb`. When `compact` is set, sheets are
+/// instead packed 1 bit per pixel (set when a sample exceeds `threshold`)
+/// into a single shared storage buffer, trading the ability to sample
+/// with hardware bilinear filtering for roughly 32x less GPU memory.
+pub fn load_fonts(
+    device:    &wgpu::Device,
+    queue:     &wgpu::Queue,
+    compact:   bool,
+    threshold: u8,
+) -> Fonts {
     // All fonts
     let font_data = [
         include_bytes!("../fonts/4x6.png").as_slice(),
@@ -59,56 +516,9 @@ pub fn load_fonts(device: &wgpu::Device, queue: &wgpu::Queue) -> Fonts {
         include_bytes!("../fonts/24x36.png").as_slice(),
     ];
 
-    // Create the sampler
-    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter:     wgpu::FilterMode::Linear,
-            min_filter:     wgpu::FilterMode::Nearest,
-            mipmap_filter:  wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-    // Construct the bind group layout for all fonts
-    let bind_group_layout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding:    0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    count:      None,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled:   false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type:    wgpu::TextureSampleType::Float {
-                            filterable: true
-                        },
-                    },
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding:    1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    count:      None,
-                    ty: wgpu::BindingType::Sampler(
-                        wgpu::SamplerBindingType::Filtering),
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding:    2,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    count:      None,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: NonZeroU64::new(8),
-                    },
-                },
-            ],
-            label: None,
-        });
-
-    // Create font database
+    let mut atlas = Atlas::new(device);
     let mut fonts = Vec::new();
+    let mut bitmap_data = Vec::new();
 
     // Load every font
     for bytes in font_data {
@@ -126,75 +536,173 @@ pub fn load_fonts(device: &wgpu::Device, queue: &wgpu::Queue) -> Fonts {
         assert!(dimensions.0 % 16 == 0 && dimensions.1 % 16 == 0,
             "Yucky font file format");
 
-        // Create a new texture capable of holding the font bitmap
-        let texture = device.create_texture_with_data(
-            &queue,
-            &wgpu::TextureDescriptor {
-                size: wgpu::Extent3d {
-                    width:                 dimensions.0,
-                    height:                dimensions.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count:    1,
-                dimension:       wgpu::TextureDimension::D2,
-                format:          wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage:           wgpu::TextureUsages::TEXTURE_BINDING,
-                label:           None,
-                view_formats:    &[],
-            },
-            &rgba,
-        );
+        let (atlas_origin, atlas_scale, compact_sheet) = if compact {
+            let (bits, row_bytes) =
+                pack_1bpp(&rgba, dimensions.0, dimensions.1, threshold);
 
-        // Get a view of the texture
-        let texture_view = texture.create_view(
-            &wgpu::TextureViewDescriptor::default());
+            let sheet = CompactSheet {
+                bit_offset: bitmap_data.len() as u32,
+                row_bytes,
+            };
+            bitmap_data.extend_from_slice(&bits);
 
-        // Create a buffer for the uniform which holds the text size
-        let buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: None,
-                usage: wgpu::BufferUsages::UNIFORM,
-                contents: (
-                    (dimensions.0 / 16) as f32,
-                    (dimensions.1 / 16) as f32,
-                ).cast(),
-            });
+            ((0., 0.), (0., 0.), Some(sheet))
+        } else {
+            // Pack the whole 16x16 glyph sheet into the shared atlas as
+            // one shelf item
+            let (atlas_origin, atlas_max) =
+                atlas.pack(device, queue, dimensions.0, dimensions.1, &rgba);
+            let atlas_scale = (atlas_max.0 - atlas_origin.0,
+                               atlas_max.1 - atlas_origin.1);
+
+            (atlas_origin, atlas_scale, None)
+        };
 
         // Save the font info
         fonts.push(Font {
-            width:      dimensions.0 / 16,
-            height:     dimensions.0 / 16,
-            bind_group: device.create_bind_group(
-                &wgpu::BindGroupDescriptor {
-                    layout:  &bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding:  0,
-                            resource: wgpu::BindingResource::TextureView(
-                                &texture_view),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding:  1,
-                            resource: wgpu::BindingResource::Sampler(&sampler),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                                buffer: &buffer,
-                                offset: 0,
-                                size:   NonZeroU64::new(8),
-                            }),
-                        },
-                    ],
-                    label: None,
-                }),
+            width:  dimensions.0 / 16,
+            height: dimensions.1 / 16,
+            atlas_origin,
+            atlas_scale,
+            compact: compact_sheet,
         });
     }
 
-    Fonts {
-        fonts,
-        bind_group_layout,
+    let bitmap_buffer = compact.then(|| {
+        use wgpu::util::DeviceExt;
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label:    None,
+            usage:    wgpu::BufferUsages::STORAGE,
+            contents: &bitmap_data,
+        })
+    });
+
+    Fonts { atlas, fonts, bitmap_buffer }
+}
+
+/// Metrics describing the placement and advance of a single rasterized
+/// glyph, as produced by the rasterizer
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphMetrics {
+    /// Width of the glyph's coverage bitmap, in pixels
+    pub width: u32,
+
+    /// Height of the glyph's coverage bitmap, in pixels
+    pub height: u32,
+
+    /// Horizontal offset from the pen position to the left edge of the
+    /// glyph's bitmap, in pixels
+    pub xmin: i32,
+
+    /// Vertical offset from the baseline to the bottom edge of the
+    /// glyph's bitmap, in pixels
+    pub ymin: i32,
+
+    /// Horizontal distance to advance the pen after drawing this glyph,
+    /// in pixels
+    pub advance_width: f32,
+}
+
+/// A glyph which has been rasterized and packed into the shared atlas
+#[derive(Clone, Copy, Debug)]
+pub struct Glyph {
+    /// Pixel-space coordinates of the top-left corner of the glyph in
+    /// the atlas texture, as it was when packed -- normalize by
+    /// [`Atlas::size`] at render time, since the atlas may have grown
+    /// (and moved this glyph's backing texture, though not its pixel
+    /// position) since this `Glyph` was cached
+    pub uv_min: (f32, f32),
+
+    /// Pixel-space coordinates of the bottom-right corner of the glyph
+    /// in the atlas texture
+    pub uv_max: (f32, f32),
+
+    /// Metrics for this glyph
+    pub metrics: GlyphMetrics,
+}
+
+/// A TrueType/OpenType font which is rasterized on the CPU and packed
+/// into the shared [`Atlas`] on demand, unlike [`Font`] which is a
+/// pre-baked monospaced bitmap sheet loaded in its entirety up front
+pub struct TtfFont {
+    /// The vector font glyphs are rasterized from
+    rasterizer: fontdue::Font,
+
+    /// Em size, in pixels, that glyphs are rasterized at
+    px_per_em: f32,
+
+    /// Glyphs which have already been rasterized and packed, keyed by
+    /// codepoint
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl TtfFont {
+    /// Look up the glyph for `codepoint`, rasterizing and packing it into
+    /// `atlas` if it hasn't been requested yet
+    pub fn glyph(
+        &mut self,
+        device:    &wgpu::Device,
+        atlas:     &mut Atlas,
+        queue:     &wgpu::Queue,
+        codepoint: char,
+    ) -> Glyph {
+        if let Some(&glyph) = self.glyphs.get(&codepoint) {
+            return glyph;
+        }
+
+        // Rasterize the glyph's coverage bitmap at our configured size
+        let (metrics, coverage) =
+            self.rasterizer.rasterize(codepoint, self.px_per_em);
+
+        // Glyphs with no pixels (eg space) still need an advance width,
+        // but there's nothing to pack or upload
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+
+        let (uv_min, uv_max) = if width == 0 || height == 0 {
+            ((0., 0.), (0., 0.))
+        } else {
+            // Coverage comes back as a single byte per pixel, expand it
+            // to RGBA so it packs into the same atlas as the pre-baked
+            // bitmap fonts
+            let mut rgba = Vec::with_capacity(coverage.len() * 4);
+            for cov in &coverage {
+                rgba.extend_from_slice(&[*cov, *cov, *cov, *cov]);
+            }
+
+            atlas.pack(device, queue, width, height, &rgba)
+        };
+
+        let glyph = Glyph {
+            uv_min,
+            uv_max,
+            metrics: GlyphMetrics {
+                width,
+                height,
+                xmin:          metrics.xmin,
+                ymin:          metrics.ymin,
+                advance_width: metrics.advance_width,
+            },
+        };
+
+        self.glyphs.insert(codepoint, glyph);
+        glyph
     }
 }
 
+/// Parse a TrueType/OpenType font from `font_bytes`, ready to rasterize
+/// glyphs on demand
+///
+/// `px_per_em` is the em size, in pixels, that glyphs will be rasterized
+/// at; unlike [`load_fonts`] this isn't restricted to a fixed set of
+/// pre-baked sizes.
+pub fn load_ttf(font_bytes: &[u8], px_per_em: f32) -> TtfFont {
+    let rasterizer = fontdue::Font::from_bytes(
+        font_bytes, fontdue::FontSettings::default())
+        .expect("Failed to parse TTF/OTF font");
+
+    TtfFont {
+        rasterizer,
+        px_per_em,
+        glyphs: HashMap::new(),
+    }
+}
@@ -0,0 +1,150 @@
+//! Text layout: line wrapping and horizontal/vertical alignment on top of
+//! a loaded font, producing positioned glyph quads ready for rendering
+
+use crate::fonts::{Fonts, FontSize};
+
+/// Horizontal alignment of a laid-out block of text within its bounding
+/// box
+#[derive(Clone, Copy)]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Vertical alignment of a laid-out block of text within its bounding box
+#[derive(Clone, Copy)]
+pub enum VAlign {
+    Top,
+    Middle,
+    Baseline,
+    Bottom,
+}
+
+/// A rectangular region to lay text out within, in pixels
+#[derive(Clone, Copy)]
+pub struct BoundingBox {
+    pub x:      f32,
+    pub y:      f32,
+    pub width:  f32,
+    pub height: f32,
+}
+
+/// A single glyph quad placed by [`layout`], with no color applied yet
+///
+/// For an atlas-backed font, `uv_min`/`uv_max` are pixel-space coordinates
+/// within the shared atlas texture, as it was when packed -- normalized by
+/// [`crate::fonts::Atlas::size`] at render time, since the atlas may have
+/// grown since. For a compact (1bpp) font, they instead hold the glyph's
+/// pixel-space top-left/bottom-right within that font's packed sheet,
+/// and `compact` describes where that sheet lives in the shared bitmap
+/// buffer.
+#[derive(Clone, Copy)]
+pub struct PositionedGlyph {
+    pub pos_min: (f32, f32),
+    pub pos_max: (f32, f32),
+    pub uv_min:  (f32, f32),
+    pub uv_max:  (f32, f32),
+    pub compact: Option<crate::fonts::CompactSheet>,
+}
+
+/// Lay `text` out against the monospaced bitmap font `font_size`, inside
+/// `bbox`, wrapping on whitespace and applying the given alignment
+///
+/// Lines are broken whenever the next word would push a line's
+/// accumulated advance width past `bbox.width`. Each line's width is
+/// tracked separately so it can be centered/right-aligned independent of
+/// the other lines.
+pub fn layout(
+    fonts:     &Fonts,
+    font_size: FontSize,
+    text:      &str,
+    bbox:      BoundingBox,
+    halign:    HAlign,
+    valign:    VAlign,
+) -> Vec<PositionedGlyph> {
+    let font     = &fonts.fonts[font_size as usize];
+    let glyph_w  = font.width  as f32;
+    let glyph_h  = font.height as f32;
+
+    // Break `text` into lines, wrapping on whitespace when the
+    // accumulated advance would overflow the box
+    let mut lines: Vec<String> = Vec::new();
+    let mut line  = String::new();
+    let mut line_width = 0.;
+
+    for word in text.split(char::is_whitespace) {
+        let word_width  = word.chars().count() as f32 * glyph_w;
+        let space_width = if line.is_empty() { 0. } else { glyph_w };
+
+        if !line.is_empty() &&
+            line_width + space_width + word_width > bbox.width {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0.;
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+            line_width += glyph_w;
+        }
+        line.push_str(word);
+        line_width += word_width;
+    }
+    lines.push(line);
+
+    let line_widths: Vec<f32> = lines.iter()
+        .map(|l| l.chars().count() as f32 * glyph_w)
+        .collect();
+
+    let total_height = lines.len() as f32 * glyph_h;
+
+    // Monospaced bitmap fonts have no separate ascent/descent, so treat
+    // the full cell height as the ascent above the baseline
+    let start_y = match valign {
+        VAlign::Top      => bbox.y,
+        VAlign::Middle   => bbox.y + (bbox.height - total_height) / 2.,
+        VAlign::Baseline => bbox.y - glyph_h,
+        VAlign::Bottom   => bbox.y + bbox.height - total_height,
+    };
+
+    let mut glyphs = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        let line_width = line_widths[row];
+        let start_x = match halign {
+            HAlign::Left   => bbox.x,
+            HAlign::Center => bbox.x + (bbox.width - line_width) / 2.,
+            HAlign::Right  => bbox.x + bbox.width - line_width,
+        };
+
+        let y = start_y + row as f32 * glyph_h;
+
+        for (col, ch) in line.chars().enumerate() {
+            let byte     = ch as u32;
+            let (c, r)   = ((byte % 16) as f32, (byte / 16) as f32);
+            let x        = start_x + col as f32 * glyph_w;
+
+            // Compact fonts have no atlas rect, so their uv_min/uv_max
+            // instead carry pixel-space coordinates within the sheet,
+            // which the shader indexes into `compact`'s bitmap directly
+            let (uv_min, uv_max) = if font.compact.is_some() {
+                ((c * glyph_w,       r * glyph_h),
+                 ((c + 1.) * glyph_w, (r + 1.) * glyph_h))
+            } else {
+                ((font.atlas_origin.0 + font.atlas_scale.0 * c       / 16.,
+                  font.atlas_origin.1 + font.atlas_scale.1 * r       / 16.),
+                 (font.atlas_origin.0 + font.atlas_scale.0 * (c + 1.) / 16.,
+                  font.atlas_origin.1 + font.atlas_scale.1 * (r + 1.) / 16.))
+            };
+
+            glyphs.push(PositionedGlyph {
+                pos_min: (x, y),
+                pos_max: (x + glyph_w, y + glyph_h),
+                uv_min,
+                uv_max,
+                compact: font.compact,
+            });
+        }
+    }
+
+    glyphs
+}
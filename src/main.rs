@@ -1,11 +1,14 @@
 mod fonts;
+mod layout;
 
 use std::mem::size_of;
 use std::num::NonZeroU64;
 use std::collections::VecDeque;
+use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoop;
 use winit::window::WindowBuilder;
 use fonts::FontSize;
+use layout::{BoundingBox, HAlign, VAlign};
 
 const WIDTH:  u32 = 1440;
 const HEIGHT: u32 =  900;
@@ -33,6 +36,16 @@ unsafe trait Castable: Copy {
     }
 }
 
+/// Reinterpret a slice of `Castable` values as raw bytes, for uploading
+/// to a GPU buffer
+fn cast_slice<T: Castable>(values: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8,
+            std::mem::size_of_val(values))
+    }
+}
+
+unsafe impl Castable for f32 {}
 unsafe impl Castable for (f32, f32) {}
 
 #[derive(Clone, Copy)]
@@ -40,19 +53,176 @@ unsafe impl Castable for (f32, f32) {}
 struct Globals {
     win_width:  f32,
     win_height: f32,
+
+    /// Gamma used to build the coverage correction LUT, kept here purely
+    /// for visibility/debugging -- the shader reads corrected alpha out
+    /// of the LUT rather than recomputing `pow` from this value
+    gamma: f32,
+
+    /// Current width/height, in pixels, of the (square) shared font atlas
+    /// texture -- glyph instances carry pixel-space atlas rects rather
+    /// than normalized UVs, since the atlas can grow after they're
+    /// packed, so the shader divides by this to normalize them instead
+    atlas_size: f32,
 }
 
 unsafe impl Castable for Globals {}
 
+/// Size, in bytes, of the gamma correction LUT (256 `f32` entries)
+const GAMMA_LUT_SIZE: u64 = 256 * 4;
+
+/// Pick a gamma for coverage correction, blended between the higher
+/// contrast dark-text-on-light-background needs and the lower contrast
+/// light-text-on-dark-background needs, based on how much brighter the
+/// foreground is than the background
+fn gamma_for_luminance(fg_luminance: f32, bg_luminance: f32) -> f32 {
+    const DARK_ON_LIGHT: f32 = 2.4;
+    const LIGHT_ON_DARK: f32 = 1.4;
+
+    let t = ((fg_luminance - bg_luminance) + 1.) / 2.;
+    DARK_ON_LIGHT + (LIGHT_ON_DARK - DARK_ON_LIGHT) * t.clamp(0., 1.)
+}
+
+/// Perceptual luminance of an `rgba` color
+fn luminance(rgba: (f32, f32, f32, f32)) -> f32 {
+    0.2126 * rgba.0 + 0.7152 * rgba.1 + 0.0722 * rgba.2
+}
+
+/// Precompute a 256-entry table mapping raw glyph coverage to
+/// gamma-corrected alpha, so `fs_main` can look it up instead of calling
+/// `pow` per pixel
+fn build_gamma_lut(gamma: f32) -> [f32; 256] {
+    let mut lut = [0.0f32; 256];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        *entry = (i as f32 / 255.).powf(1. / gamma);
+    }
+    lut
+}
+
+/// A single glyph quad, ready for the GPU to instance
+///
+/// One of these is uploaded per visible glyph; the vertex shader draws
+/// six vertices (two triangles) per instance, picking one of the four
+/// corners of `pos_min..pos_max`/`uv_min..uv_max` based on
+/// `vertex_index`.
 #[derive(Clone, Copy)]
 #[repr(C)]
-struct PushConstants {
+struct GlyphInstance {
+    pos_min: (f32, f32),
+    pos_max: (f32, f32),
+    uv_min:  (f32, f32),
+    uv_max:  (f32, f32),
+    color:   [u8; 4],
+
+    /// Byte offset of this glyph's font sheet within the shared compact
+    /// (1bpp) bitmap buffer, and bytes per row of that sheet; both zero
+    /// for a glyph coming from the atlas instead, since a real sheet
+    /// always has at least one row byte
+    bit_offset: u32,
+    row_bytes:  u32,
+}
+
+unsafe impl Castable for GlyphInstance {}
+
+impl GlyphInstance {
+    /// Vertex buffer layout describing a `GlyphInstance`, stepped once
+    /// per instance rather than once per vertex
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<GlyphInstance>() as u64,
+            step_mode:    wgpu::VertexStepMode::Instance,
+            attributes:   &wgpu::vertex_attr_array![
+                0 => Float32x2, // pos_min
+                1 => Float32x2, // pos_max
+                2 => Float32x2, // uv_min
+                3 => Float32x2, // uv_max
+                4 => Unorm8x4,  // color
+                5 => Uint32,    // bit_offset
+                6 => Uint32,    // row_bytes
+            ],
+        }
+    }
+}
+
+/// Run `text` through [`layout::layout`] and tint every resulting glyph
+/// quad `rgba`, appending the finished instances to `instances`
+fn layout_string(
+    fonts:     &fonts::Fonts,
+    font_size: FontSize,
+    bbox:      BoundingBox,
     rgba:      (f32, f32, f32, f32),
-    xy:        (f32, f32),
-    offset:    u32,
+    text:      &str,
+    instances: &mut Vec<GlyphInstance>,
+) {
+    let color = [
+        (rgba.0 * 255.) as u8, (rgba.1 * 255.) as u8,
+        (rgba.2 * 255.) as u8, (rgba.3 * 255.) as u8,
+    ];
+
+    for glyph in layout::layout(
+        fonts, font_size, text, bbox, HAlign::Left, VAlign::Top) {
+        let (bit_offset, row_bytes) = glyph.compact
+            .map_or((0, 0), |sheet| (sheet.bit_offset, sheet.row_bytes));
+
+        instances.push(GlyphInstance {
+            pos_min: glyph.pos_min,
+            pos_max: glyph.pos_max,
+            uv_min:  glyph.uv_min,
+            uv_max:  glyph.uv_max,
+            color,
+            bit_offset,
+            row_bytes,
+        });
+    }
 }
 
-unsafe impl Castable for PushConstants {}
+/// Lay a proportional TTF string out along a single line starting at pen
+/// position `origin`, rasterizing and packing glyphs into `atlas` on
+/// demand, and append the finished instances to `instances`
+///
+/// Unlike [`layout_string`], this doesn't go through the wrapping/
+/// alignment-aware `layout` module -- `TtfFont` glyphs carry their own
+/// per-glyph advance width and offsets, which is the whole point of the
+/// rasterizer path, but `layout` only understands the fixed-advance
+/// monospaced bitmap fonts.
+fn layout_ttf_string(
+    device:    &wgpu::Device,
+    atlas:     &mut fonts::Atlas,
+    queue:     &wgpu::Queue,
+    ttf_font:  &mut fonts::TtfFont,
+    origin:    (f32, f32),
+    rgba:      (f32, f32, f32, f32),
+    text:      &str,
+    instances: &mut Vec<GlyphInstance>,
+) {
+    let color = [
+        (rgba.0 * 255.) as u8, (rgba.1 * 255.) as u8,
+        (rgba.2 * 255.) as u8, (rgba.3 * 255.) as u8,
+    ];
+
+    let mut pen_x = origin.0;
+    for ch in text.chars() {
+        let glyph = ttf_font.glyph(device, atlas, queue, ch);
+        let m = glyph.metrics;
+
+        if m.width > 0 && m.height > 0 {
+            let x = pen_x + m.xmin as f32;
+            let y = origin.1 - (m.ymin as f32 + m.height as f32);
+
+            instances.push(GlyphInstance {
+                pos_min:    (x, y),
+                pos_max:    (x + m.width as f32, y + m.height as f32),
+                uv_min:     glyph.uv_min,
+                uv_max:     glyph.uv_max,
+                color,
+                bit_offset: 0,
+                row_bytes:  0,
+            });
+        }
+
+        pen_x += m.advance_width;
+    }
+}
 
 #[pollster::main]
 async fn main() {
@@ -65,7 +235,6 @@ async fn main() {
     let window = measure!("Creating winit::Window", {
         WindowBuilder::new()
             .with_inner_size(winit::dpi::PhysicalSize::new(WIDTH, HEIGHT))
-            .with_resizable(false)
             .build(&event_loop)
             .expect("Failed to build window")
     });
@@ -95,14 +264,8 @@ async fn main() {
 
     // Get access to the device and a queue to issue commands to it
     let (device, queue) = measure!("Creating wgpu::Device", {
-        adapter.request_device(&wgpu::DeviceDescriptor {
-            features: wgpu::Features::PUSH_CONSTANTS,
-            limits:   wgpu::Limits {
-                max_push_constant_size: size_of::<PushConstants>() as u32,
-                ..Default::default()
-            },
-            ..Default::default()
-        }, None).await.expect("Failed to create Device")
+        adapter.request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await.expect("Failed to create Device")
     });
 
     // Compile the shader
@@ -110,19 +273,29 @@ async fn main() {
         device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"))
     });
 
-    // Load the font
-    let fonts = measure!("Loading fonts", {
-        fonts::load_fonts(&device, &queue)
+    // Load the font. Bitmap fonts are left in their full `Rgba8UnormSrgb`
+    // atlas form rather than the compact 1bpp format, since this demo has
+    // plenty of atlas room to spare.
+    let mut fonts = measure!("Loading fonts", {
+        fonts::load_fonts(&device, &queue, false, 128)
     });
 
-    // Create buffer for text
-    const FONT_BUFFER_SIZE: u64 = 1024 * 1024;
-    let text_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label:              None,
-        size:               FONT_BUFFER_SIZE,
-        usage:              wgpu::BufferUsages::STORAGE |
-                            wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false,
+    // Load a TTF/OTF font too, rasterized on demand at an arbitrary em
+    // size rather than restricted to `load_fonts`'s ten pre-baked sizes
+    let mut ttf_font = measure!("Loading TTF font", {
+        fonts::load_ttf(include_bytes!("../fonts/demo.ttf"), 32.)
+    });
+
+    // The compact bitmap buffer is only populated when fonts are loaded
+    // with `compact = true`; bind a harmless empty one otherwise so the
+    // pipeline's bind group layout doesn't need to vary by mode
+    let compact_bitmap_buffer = fonts.bitmap_buffer.unwrap_or_else(|| {
+        use wgpu::util::DeviceExt;
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label:    None,
+            usage:    wgpu::BufferUsages::STORAGE,
+            contents: &[0u8; 4],
+        })
     });
 
     // Create buffer for globals to the shader
@@ -134,7 +307,16 @@ async fn main() {
         mapped_at_creation: false,
     });
 
-    // Create the bind group layout for the font
+    // Create buffer for the gamma-correction coverage LUT
+    let gamma_lut_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label:              None,
+        size:               GAMMA_LUT_SIZE,
+        usage:              wgpu::BufferUsages::STORAGE |
+                            wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    // Create the bind group layout for the globals and gamma LUT
     let bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
@@ -142,23 +324,35 @@ async fn main() {
                     binding: 0,
                     visibility: wgpu::ShaderStages::VERTEX,
                     count: None,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size:
+                            NonZeroU64::new(size_of::<Globals>() as u64),
+                    }
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    count: None,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage {
                             read_only: true,
                         },
                         has_dynamic_offset: false,
-                        min_binding_size: NonZeroU64::new(FONT_BUFFER_SIZE),
+                        min_binding_size: NonZeroU64::new(GAMMA_LUT_SIZE),
                     }
                 },
                 wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
                     count: None,
                     ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
+                        ty: wgpu::BufferBindingType::Storage {
+                            read_only: true,
+                        },
                         has_dynamic_offset: false,
-                        min_binding_size:
-                            NonZeroU64::new(size_of::<Globals>() as u64),
+                        min_binding_size: None,
                     }
                 },
             ],
@@ -174,9 +368,10 @@ async fn main() {
                     binding:  0,
                     resource: wgpu::BindingResource::Buffer(
                         wgpu::BufferBinding {
-                            buffer: &text_buffer,
+                            buffer: &global_buffer,
                             offset: 0,
-                            size:   NonZeroU64::new(FONT_BUFFER_SIZE),
+                            size:
+                                NonZeroU64::new(size_of::<Globals>() as u64),
                         }
                     ),
                 },
@@ -184,10 +379,19 @@ async fn main() {
                     binding:  1,
                     resource: wgpu::BindingResource::Buffer(
                         wgpu::BufferBinding {
-                            buffer: &global_buffer,
+                            buffer: &gamma_lut_buffer,
                             offset: 0,
-                            size:
-                                NonZeroU64::new(size_of::<Globals>() as u64),
+                            size:   NonZeroU64::new(GAMMA_LUT_SIZE),
+                        }
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding:  2,
+                    resource: wgpu::BindingResource::Buffer(
+                        wgpu::BufferBinding {
+                            buffer: &compact_bitmap_buffer,
+                            offset: 0,
+                            size:   None,
                         }
                     ),
                 },
@@ -201,15 +405,10 @@ async fn main() {
             &wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    &fonts.bind_group_layout,
+                    &fonts.atlas.bind_group_layout,
                     &bind_group_layout,
                 ],
-                push_constant_ranges: &[
-                    wgpu::PushConstantRange {
-                        stages: wgpu::ShaderStages::VERTEX,
-                        range:  0..size_of::<PushConstants>() as u32,
-                    }
-                ],
+                push_constant_ranges: &[],
             });
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
@@ -217,7 +416,7 @@ async fn main() {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[],
+                buffers: &[GlyphInstance::layout()],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -247,25 +446,21 @@ async fn main() {
         })
     });
 
-    // Configure the surface
+    // Configure the surface. Kept around (and mutated in place) so the
+    // resize handler below can reconfigure with new dimensions.
+    let mut config = wgpu::SurfaceConfiguration {
+        usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format:       wgpu::TextureFormat::Bgra8UnormSrgb,
+        width:        window.inner_size().width,
+        height:       window.inner_size().height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode:   wgpu::CompositeAlphaMode::Opaque,
+        view_formats: Vec::new(),
+    };
     measure!("Configuring wgpu::Surface", {
-        surface.configure(&device, &wgpu::SurfaceConfiguration {
-            usage:        wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format:       wgpu::TextureFormat::Bgra8UnormSrgb,
-            width:        window.inner_size().width,
-            height:       window.inner_size().height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode:   wgpu::CompositeAlphaMode::Opaque,
-            view_formats: Vec::new(),
-        })
+        surface.configure(&device, &config)
     });
 
-    let globals = Globals {
-        win_width:  WIDTH  as f32,
-        win_height: HEIGHT as f32,
-    };
-    queue.write_buffer(&global_buffer, 0, globals.cast());
-
     let all_fonts = [
         FontSize::Size4x6,
         FontSize::Size6x8,
@@ -278,95 +473,180 @@ async fn main() {
         FontSize::Size16x24,
         FontSize::Size24x36,
     ];
-    let mut strings = Vec::new();
-    for _ in 0..100 {
-        strings.push((
-            all_fonts[rand::random::<usize>() % all_fonts.len()],
-            PushConstants {
-                xy:     ((rand::random::<u32>() % WIDTH) as f32, (rand::random::<u32>() % HEIGHT) as f32),
-                rgba:   (rand::random::<f32>(), rand::random::<f32>(), rand::random::<f32>(), rand::random::<f32>()),
-                offset: 0,
-            },
-            b"Hello world".as_slice(),
-        ));
-    }
 
-    // Allocate all text data in one big buffer
-    let mut text_data = Vec::new();
-    for (_, pc, msg) in &mut strings {
-        pc.offset = text_data.len() as u32 / 4;
-        text_data.extend_from_slice(*msg);
-        text_data.resize((text_data.len() + 3) & !3, 0u8);
+    // Pick every string's font, position, and color up front so we can
+    // derive a gamma from their average luminance before building the LUT
+    let strings: Vec<_> = (0..100).map(|_| {
+        let font_size = all_fonts[rand::random::<usize>() % all_fonts.len()];
+        let bbox = BoundingBox {
+            x:      (rand::random::<u32>() % WIDTH)  as f32,
+            y:      (rand::random::<u32>() % HEIGHT) as f32,
+            width:  WIDTH  as f32,
+            height: HEIGHT as f32,
+        };
+        let rgba = (rand::random::<f32>(), rand::random::<f32>(),
+                    rand::random::<f32>(), rand::random::<f32>());
+
+        (font_size, bbox, rgba)
+    }).collect();
+
+    // The render pass clears to black, so that's our background luminance
+    const BG_LUMINANCE: f32 = 0.;
+    let avg_fg_luminance = strings.iter()
+        .map(|(_, _, rgba)| luminance(*rgba))
+        .sum::<f32>() / strings.len() as f32;
+    let gamma = gamma_for_luminance(avg_fg_luminance, BG_LUMINANCE);
+
+    // Build the instance buffer for every visible glyph, up front, since
+    // none of this demo's text ever changes. The TTF label below
+    // rasterizes and packs its glyphs into the shared atlas as it goes, so
+    // all of this has to finish before `atlas_size` below is read.
+    let mut instances = Vec::new();
+    for (font_size, bbox, rgba) in strings {
+        layout_string(&fonts, font_size, bbox, rgba, "Hello world",
+            &mut instances);
     }
-    queue.write_buffer(&text_buffer, 0, text_data.as_slice());
+
+    // One proportionally-spaced label rendered through the TTF rasterizer
+    // path, to exercise it end to end alongside the baked bitmap fonts
+    layout_ttf_string(&device, &mut fonts.atlas, &queue, &mut ttf_font,
+        (16., 48.), (1., 1., 1., 1.), "Proportional TTF text", &mut instances);
+
+    // Read back the atlas's final size now that nothing packs into it
+    // again -- glyph instances hold pixel-space atlas rects (since the
+    // atlas may have grown after they were packed), so this is what the
+    // shader normalizes them by
+    let atlas_size = fonts.atlas.size() as f32;
+
+    let write_globals = |queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration| {
+        let globals = Globals {
+            win_width:  config.width  as f32,
+            win_height: config.height as f32,
+            gamma,
+            atlas_size,
+        };
+        queue.write_buffer(&global_buffer, 0, globals.cast());
+    };
+    write_globals(&queue, &config);
+    queue.write_buffer(&gamma_lut_buffer, 0,
+        cast_slice(&build_gamma_lut(gamma)));
+
+    let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label:              None,
+        size:               std::mem::size_of_val(instances.as_slice()) as u64,
+        usage:              wgpu::BufferUsages::VERTEX |
+                            wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    queue.write_buffer(&instance_buffer, 0, cast_slice(&instances));
 
     let mut frame_times = VecDeque::new();
+    let mut frame       = 0u64;
     let it = std::time::Instant::now();
-    for frame in 0u64.. {
-        // Get the current vsync texture to present to for the surface (window)
-        let texture = surface.get_current_texture()
-            .expect("Failed to get current texture");
 
-        // Create a view of the texture
-        let tv = texture.texture.create_view(&Default::default());
+    event_loop.run(move |event, _, control_flow| {
+        control_flow.set_poll();
 
-        // Create a new command encoder
-        let mut commands = device.create_command_encoder(&Default::default());
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => control_flow.set_exit(),
 
-        {
-            // Start a render pass, clearing the screen to black
-            let mut render_pass = commands.begin_render_pass(
-                &wgpu::RenderPassDescriptor {
-                    label: None,
-                    color_attachments: &[
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: &tv,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    r: 0.0, g: 0.0, b: 0.0, a: 1.0
-                                }),
-                                store: true,
-                            },
-                        })
-                    ],
-                    depth_stencil_attachment: None,
-                });
-
-            render_pass.set_pipeline(&render_pipeline);
-            render_pass.set_bind_group(1, &bind_group, &[]);
-
-            for (font_size, push_constants, msg) in &strings {
-                // Set the font size
-                render_pass.set_bind_group(0,
-                    &fonts.fonts[*font_size as usize].bind_group, &[]);
-
-                // Write the constants
-                render_pass.set_push_constants(
-                    wgpu::ShaderStages::VERTEX, 0, push_constants.cast());
-
-                // Draw the text
-                render_pass.draw(0..(msg.len() * 6) as u32, 0..1);
-            }
-        }
+                WindowEvent::Resized(size) => {
+                    config.width  = size.width.max(1);
+                    config.height = size.height.max(1);
+                    surface.configure(&device, &config);
+                    write_globals(&queue, &config);
+                }
 
-        // Send the queue to the GPU
-        queue.submit(Some(commands.finish()));
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    config.width  = new_inner_size.width.max(1);
+                    config.height = new_inner_size.height.max(1);
+                    surface.configure(&device, &config);
+                    write_globals(&queue, &config);
+                }
 
-        // Present the texture to the surface
-        texture.present();
-
-        while frame_times.len() >= 128 {
-            frame_times.pop_front();
-        }
+                _ => {}
+            },
 
-        frame_times.push_back(it.elapsed().as_secs_f64());
+            Event::MainEventsCleared => window.request_redraw(),
+
+            Event::RedrawRequested(_) => {
+                // Get the current vsync texture to present to for the
+                // surface (window), reconfiguring and retrying on any
+                // recoverable error instead of panicking
+                let texture = match surface.get_current_texture() {
+                    Ok(texture) => texture,
+                    Err(wgpu::SurfaceError::Lost |
+                        wgpu::SurfaceError::Outdated) => {
+                        surface.configure(&device, &config);
+                        return;
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => return,
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        control_flow.set_exit();
+                        return;
+                    }
+                };
+
+                // Create a view of the texture
+                let tv = texture.texture.create_view(&Default::default());
+
+                // Create a new command encoder
+                let mut commands =
+                    device.create_command_encoder(&Default::default());
+
+                {
+                    // Start a render pass, clearing the screen to black
+                    let mut render_pass = commands.begin_render_pass(
+                        &wgpu::RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[
+                                Some(wgpu::RenderPassColorAttachment {
+                                    view: &tv,
+                                    resolve_target: None,
+                                    ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                                            r: 0.0, g: 0.0, b: 0.0, a: 1.0
+                                        }),
+                                        store: true,
+                                    },
+                                })
+                            ],
+                            depth_stencil_attachment: None,
+                        });
+
+                    render_pass.set_pipeline(&render_pipeline);
+                    render_pass.set_bind_group(0, &fonts.atlas.bind_group, &[]);
+                    render_pass.set_bind_group(1, &bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+
+                    // Every glyph, from every string, in a single
+                    // instanced draw
+                    render_pass.draw(0..6, 0..instances.len() as u32);
+                }
+
+                // Send the queue to the GPU
+                queue.submit(Some(commands.finish()));
+
+                // Present the texture to the surface
+                texture.present();
+
+                while frame_times.len() >= 128 {
+                    frame_times.pop_front();
+                }
+
+                frame_times.push_back(it.elapsed().as_secs_f64());
+
+                if frame % 128 == 0 {
+                    let fps = (frame_times.len() - 1) as f64 /
+                        (frame_times.back().unwrap() - frame_times.front().unwrap());
+                    println!("FPS {fps:10.4}");
+                }
+
+                frame += 1;
+            }
 
-        if frame % 128 == 0 {
-            let fps = (frame_times.len() - 1) as f64 /
-                (frame_times.back().unwrap() - frame_times.front().unwrap());
-            println!("FPS {fps:10.4}");
+            _ => {}
         }
-    }
+    });
 }
-